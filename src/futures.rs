@@ -0,0 +1,159 @@
+use crate::syscalls;
+use core::cell::Cell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+// Shared cell the kernel callback writes into. The future owns it and hands the
+// kernel a pointer to it via `subscribe`; because a pinned future cannot move,
+// that pointer stays valid until the future is dropped, and its `Drop`
+// unsubscribes.
+struct CallbackState {
+    ready: Cell<bool>,
+    values: Cell<(usize, usize, usize)>,
+}
+
+// The C callback the kernel invokes. It stores the three callback arguments and
+// flags the state ready; the next poll observes the flag and resolves.
+extern "C" fn callback(arg0: usize, arg1: usize, arg2: usize, userdata: usize) {
+    let state = unsafe { &*(userdata as *const CallbackState) };
+    state.values.set((arg0, arg1, arg2));
+    state.ready.set(true);
+}
+
+// A future that resolves when a driver fires the subscribed callback, yielding
+// the callback's `(arg0, arg1, arg2)`. The subscription is registered on the
+// first poll and torn down in `Drop`, so the kernel never calls into freed
+// stack after the future goes away.
+pub struct SubscriptionFuture {
+    driver_number: usize,
+    subscribe_number: usize,
+    state: CallbackState,
+    subscribed: bool,
+    // `state`'s address is handed to the kernel on the first poll, so the future
+    // must not move afterwards. The fields are all `Cell`/`usize`/`bool`, which
+    // would make the struct `Unpin` and let safe code move it out of a `Pin`;
+    // `PhantomPinned` makes it `!Unpin` so the pin guarantee actually holds.
+    _pin: core::marker::PhantomPinned,
+}
+
+pub fn subscribe(driver_number: usize, subscribe_number: usize) -> SubscriptionFuture {
+    SubscriptionFuture {
+        driver_number,
+        subscribe_number,
+        state: CallbackState {
+            ready: Cell::new(false),
+            values: Cell::new((0, 0, 0)),
+        },
+        subscribed: false,
+        _pin: core::marker::PhantomPinned,
+    }
+}
+
+impl Future for SubscriptionFuture {
+    type Output = (usize, usize, usize);
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        // The future is pinned, so `&self.state` will not move out from under
+        // the kernel after we register it below.
+        let this = unsafe { self.get_unchecked_mut() };
+        if !this.subscribed {
+            unsafe {
+                syscalls::subscribe(
+                    this.driver_number,
+                    this.subscribe_number,
+                    callback,
+                    &this.state as *const CallbackState as usize,
+                );
+            }
+            this.subscribed = true;
+        }
+        if this.state.ready.get() {
+            Poll::Ready(this.state.values.get())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for SubscriptionFuture {
+    fn drop(&mut self) {
+        if self.subscribed {
+            syscalls::unsubscribe(self.driver_number, self.subscribe_number);
+        }
+    }
+}
+
+// Runs two futures concurrently, resolving once both have completed. An
+// application that needs to await several drivers at once combines their
+// futures with this and still yields to the kernel between callbacks.
+pub struct Join<F1: Future, F2: Future> {
+    f1: F1,
+    o1: Option<F1::Output>,
+    f2: F2,
+    o2: Option<F2::Output>,
+}
+
+pub fn join<F1: Future, F2: Future>(f1: F1, f2: F2) -> Join<F1, F2> {
+    Join {
+        f1,
+        o1: None,
+        f2,
+        o2: None,
+    }
+}
+
+impl<F1: Future, F2: Future> Future for Join<F1, F2> {
+    type Output = (F1::Output, F2::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.o1.is_none() {
+            let f1 = unsafe { Pin::new_unchecked(&mut this.f1) };
+            if let Poll::Ready(v) = f1.poll(cx) {
+                this.o1 = Some(v);
+            }
+        }
+        if this.o2.is_none() {
+            let f2 = unsafe { Pin::new_unchecked(&mut this.f2) };
+            if let Poll::Ready(v) = f2.poll(cx) {
+                this.o2 = Some(v);
+            }
+        }
+        if this.o1.is_some() && this.o2.is_some() {
+            Poll::Ready((this.o1.take().unwrap(), this.o2.take().unwrap()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+// The executor's waker does nothing: there is no run queue to wake, since the
+// executor re-polls the top-level future after every `yieldk()`. The callback
+// that actually makes progress is driven by the kernel, not the waker.
+fn raw_waker() -> RawWaker {
+    fn no_op(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+        raw_waker()
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+    RawWaker::new(core::ptr::null(), &VTABLE)
+}
+
+// Minimal single-threaded executor: polls `future`, and whenever it is still
+// `Pending` hands control back to the kernel with `yieldk()` so a callback can
+// run. This replaces the busy `yieldk_for` spin loop for futures-based code.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = future;
+    // Safe because `future` lives on this stack frame for the rest of the
+    // function and is never moved again after this point.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(value) => return value,
+            Poll::Pending => syscalls::yieldk(),
+        }
+    }
+}
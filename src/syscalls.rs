@@ -1,4 +1,6 @@
 use core::marker::PhantomData;
+
+#[cfg(target_arch = "arm")]
 pub fn yieldk() {
     // Note: A process stops yielding when there is a callback ready to run,
     // which the kernel executes by modifying the stack frame pushed by the
@@ -21,7 +23,14 @@ pub fn yieldk() {
     // According to the AAPCS: A subroutine must preserve the contents of the
     // registers r4-r8, r10, r11 and SP (and r9 in PCS variants that designate
     // r9 as v6) As our compilation flags mark r9 as the PIC base register, it
-    // does not need to be saved. Thus we must clobber r0-3, r12, and LR
+    // does not need to be saved. Thus we must clobber r0-3, r12, and LR.
+    //
+    // The `pic_base_r9` feature reflects whether the build genuinely reserves
+    // r9 as the PIC base register. On fixed-address / non-PIC builds the
+    // compiler is free to allocate r9 for live values, and since a callback can
+    // run across the `svc 0` and clobber it, we must add r9 to the clobber list
+    // in that case so the compiler saves and restores it.
+    #[cfg(feature = "pic_base_r9")]
     unsafe {
         asm!(
             "svc 0"
@@ -30,6 +39,38 @@ pub fn yieldk() {
             : "memory", "r0", "r1", "r2", "r3", "r12", "lr"
             : "volatile");
     }
+    #[cfg(not(feature = "pic_base_r9"))]
+    unsafe {
+        asm!(
+            "svc 0"
+            :
+            :
+            : "memory", "r0", "r1", "r2", "r3", "r9", "r12", "lr"
+            : "volatile");
+    }
+}
+
+// The RISC-V backend mirrors the ARM one above, but uses the `ecall`
+// instruction instead of `svc N`. The syscall class number is passed in a4
+// (yield=0, subscribe=1, command=2, allow=3, memop=4) and the up-to-four
+// arguments in a0-a3. The `*_return` functions read all four return registers
+// a0-a3 back into a `SyscallReturn`; `memop` reads only a0.
+#[cfg(target_arch = "riscv32")]
+pub fn yieldk() {
+    // As with the ARM backend, a ready callback can run across the `ecall` and
+    // clobber any caller-saved register, so we conservatively clobber the full
+    // RISC-V caller-saved set (ra, t0-t6, a0-a7) and let the compiler save any
+    // live registers.
+    unsafe {
+        asm!(
+            "li a4, 0
+             ecall"
+            :
+            :
+            : "memory", "ra", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+              "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7"
+            : "volatile");
+    }
 }
 
 pub fn yieldk_for<F: Fn() -> bool>(cond: F) {
@@ -38,36 +79,137 @@ pub fn yieldk_for<F: Fn() -> bool>(cond: F) {
     }
 }
 
-pub unsafe fn allow(major: usize, minor: usize, slice: &[u8]) -> isize {
-    let res;
-    asm!("svc 3" : "={r0}"(res)
+// The full set of return registers captured from a syscall's `svc`/`ecall`
+// instruction. A driver that encodes more than a single error/success word in
+// its response (e.g. a success-with-two-values result, or a failure code plus
+// metadata) can decode all four registers rather than being limited to `r0`.
+pub struct SyscallReturn {
+    pub r0: isize,
+    pub r1: isize,
+    pub r2: isize,
+    pub r3: isize,
+}
+
+#[cfg(all(target_arch = "arm", feature = "pic_base_r9"))]
+pub unsafe fn allow_return(major: usize, minor: usize, slice: &[u8]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 3" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
                  : "{r0}"(major) "{r1}"(minor) "{r2}"(slice.as_ptr()) "{r3}"(slice.len())
                  : "memory"
                  : "volatile");
-    res
+    SyscallReturn { r0, r1, r2, r3 }
 }
 
-pub unsafe fn allow16(major: usize, minor: usize, slice: &[u16]) -> isize {
-    let res;
-    asm!("svc 3" : "={r0}"(res)
+#[cfg(all(target_arch = "arm", not(feature = "pic_base_r9")))]
+pub unsafe fn allow_return(major: usize, minor: usize, slice: &[u8]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 3" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
+                 : "{r0}"(major) "{r1}"(minor) "{r2}"(slice.as_ptr()) "{r3}"(slice.len())
+                 : "memory", "r9"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(target_arch = "riscv32")]
+pub unsafe fn allow_return(major: usize, minor: usize, slice: &[u8]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("ecall" : "={a0}"(r0) "={a1}"(r1) "={a2}"(r2) "={a3}"(r3)
+                 : "{a0}"(major) "{a1}"(minor) "{a2}"(slice.as_ptr()) "{a3}"(slice.len()) "{a4}"(3)
+                 : "memory"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+pub unsafe fn allow(major: usize, minor: usize, slice: &[u8]) -> isize {
+    allow_return(major, minor, slice).r0
+}
+
+#[cfg(all(target_arch = "arm", feature = "pic_base_r9"))]
+pub unsafe fn allow16_return(major: usize, minor: usize, slice: &[u16]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 3" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
                  : "{r0}"(major) "{r1}"(minor) "{r2}"(slice.as_ptr()) "{r3}"(slice.len()*2)
                  : "memory"
                  : "volatile");
-    res
+    SyscallReturn { r0, r1, r2, r3 }
 }
 
-pub unsafe fn subscribe(
+#[cfg(all(target_arch = "arm", not(feature = "pic_base_r9")))]
+pub unsafe fn allow16_return(major: usize, minor: usize, slice: &[u16]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 3" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
+                 : "{r0}"(major) "{r1}"(minor) "{r2}"(slice.as_ptr()) "{r3}"(slice.len()*2)
+                 : "memory", "r9"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(target_arch = "riscv32")]
+pub unsafe fn allow16_return(major: usize, minor: usize, slice: &[u16]) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("ecall" : "={a0}"(r0) "={a1}"(r1) "={a2}"(r2) "={a3}"(r3)
+                 : "{a0}"(major) "{a1}"(minor) "{a2}"(slice.as_ptr()) "{a3}"(slice.len()*2) "{a4}"(3)
+                 : "memory"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+pub unsafe fn allow16(major: usize, minor: usize, slice: &[u16]) -> isize {
+    allow16_return(major, minor, slice).r0
+}
+
+#[cfg(all(target_arch = "arm", feature = "pic_base_r9"))]
+pub unsafe fn subscribe_return(
     major: usize,
     minor: usize,
     cb: unsafe extern "C" fn(usize, usize, usize, usize),
     ud: usize,
-) -> isize {
-    let res;
-    asm!("svc 1" : "={r0}"(res)
+) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 1" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
                  : "{r0}"(major) "{r1}"(minor) "{r2}"(cb) "{r3}"(ud)
                  : "memory"
                  : "volatile");
-    res
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "pic_base_r9")))]
+pub unsafe fn subscribe_return(
+    major: usize,
+    minor: usize,
+    cb: unsafe extern "C" fn(usize, usize, usize, usize),
+    ud: usize,
+) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 1" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
+                 : "{r0}"(major) "{r1}"(minor) "{r2}"(cb) "{r3}"(ud)
+                 : "memory", "r9"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(target_arch = "riscv32")]
+pub unsafe fn subscribe_return(
+    major: usize,
+    minor: usize,
+    cb: unsafe extern "C" fn(usize, usize, usize, usize),
+    ud: usize,
+) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("ecall" : "={a0}"(r0) "={a1}"(r1) "={a2}"(r2) "={a3}"(r3)
+                 : "{a0}"(major) "{a1}"(minor) "{a2}"(cb) "{a3}"(ud) "{a4}"(1)
+                 : "memory"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+pub unsafe fn subscribe(
+    major: usize,
+    minor: usize,
+    cb: unsafe extern "C" fn(usize, usize, usize, usize),
+    ud: usize,
+) -> isize {
+    subscribe_return(major, minor, cb, ud).r0
 }
 
 pub fn unsubscribe(major: usize, minor: usize) -> isize {
@@ -76,19 +218,65 @@ pub fn unsubscribe(major: usize, minor: usize) -> isize {
     unsafe { subscribe(major, minor, noop_callback, 0) }
 }
 
+#[cfg(all(target_arch = "arm", feature = "pic_base_r9"))]
+pub unsafe fn command_return(major: usize, minor: usize, arg1: usize, arg2: usize) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 2" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
+                 : "{r0}"(major) "{r1}"(minor) "{r2}"(arg1) "{r3}"(arg2)
+                 : "memory"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(all(target_arch = "arm", not(feature = "pic_base_r9")))]
+pub unsafe fn command_return(major: usize, minor: usize, arg1: usize, arg2: usize) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("svc 2" : "={r0}"(r0) "={r1}"(r1) "={r2}"(r2) "={r3}"(r3)
+                 : "{r0}"(major) "{r1}"(minor) "{r2}"(arg1) "{r3}"(arg2)
+                 : "memory", "r9"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
+#[cfg(target_arch = "riscv32")]
+pub unsafe fn command_return(major: usize, minor: usize, arg1: usize, arg2: usize) -> SyscallReturn {
+    let (r0, r1, r2, r3);
+    asm!("ecall" : "={a0}"(r0) "={a1}"(r1) "={a2}"(r2) "={a3}"(r3)
+                 : "{a0}"(major) "{a1}"(minor) "{a2}"(arg1) "{a3}"(arg2) "{a4}"(2)
+                 : "memory"
+                 : "volatile");
+    SyscallReturn { r0, r1, r2, r3 }
+}
+
 pub unsafe fn command(major: usize, minor: usize, arg1: usize, arg2: usize) -> isize {
+    command_return(major, minor, arg1, arg2).r0
+}
+
+#[cfg(all(target_arch = "arm", feature = "pic_base_r9"))]
+pub unsafe fn memop(major: u32, arg1: usize) -> isize {
     let res;
-    asm!("svc 2" : "={r0}"(res)
-                 : "{r0}"(major) "{r1}"(minor) "{r2}"(arg1) "{r3}"(arg2)
+    asm!("svc 4" : "={r0}"(res)
+                 : "{r0}"(major) "{r1}"(arg1)
                  : "memory"
                  : "volatile");
     res
 }
 
+#[cfg(all(target_arch = "arm", not(feature = "pic_base_r9")))]
 pub unsafe fn memop(major: u32, arg1: usize) -> isize {
     let res;
     asm!("svc 4" : "={r0}"(res)
                  : "{r0}"(major) "{r1}"(arg1)
+                 : "memory", "r9"
+                 : "volatile");
+    res
+}
+
+#[cfg(target_arch = "riscv32")]
+pub unsafe fn memop(major: u32, arg1: usize) -> isize {
+    let res;
+    asm!("ecall" : "={a0}"(res)
+                 : "{a0}"(major) "{a1}"(arg1) "{a4}"(4)
                  : "memory"
                  : "volatile");
     res
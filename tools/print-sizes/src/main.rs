@@ -84,6 +84,36 @@ impl std::ops::Add for &ElfSizes {
     }
 }
 
+// The size bucket a single allocated section contributes to.
+enum Section {
+    Bss,
+    Data,
+    Rodata,
+    Text,
+}
+
+// Classifies a section by its type and flags rather than by name. Returns
+// `None` for sections that are not loaded into memory (no SHF_ALLOC). Keeping
+// this separate from ELF parsing lets the attribution rules be unit-tested
+// against a synthetic section table. `is_nobits` is `shtype == SHT_NOBITS`.
+fn classify_section(is_nobits: bool, flags: u64) -> Option<Section> {
+    if flags & elf::types::SHF_ALLOC.0 == 0 {
+        return None;
+    }
+    if is_nobits {
+        // .bss and friends occupy no file space but are allocated at load.
+        Some(Section::Bss)
+    } else if flags & elf::types::SHF_EXECINSTR.0 != 0 {
+        // .rodata embedded in .text is naturally counted here rather than
+        // silently dropped.
+        Some(Section::Text)
+    } else if flags & elf::types::SHF_WRITE.0 != 0 {
+        Some(Section::Data)
+    } else {
+        Some(Section::Rodata)
+    }
+}
+
 fn get_sizes(path: &std::path::Path) -> ElfSizes {
     let file = elf::File::open_path(path).expect("Unable to open example binary");
     let mut sizes = ElfSizes {
@@ -92,13 +122,18 @@ fn get_sizes(path: &std::path::Path) -> ElfSizes {
         rodata: 0,
         text: 0,
     };
+    // Rather than matching four hard-coded section names, classify every
+    // allocated section (SHF_ALLOC) by its type and flags. This keeps .rodata
+    // attributed correctly even when the linker script gives it a name we don't
+    // recognize.
     for section in file.sections {
-        match section.shdr.name.as_ref() {
-            ".bss" => sizes.bss = section.shdr.size,
-            ".data" => sizes.data = section.shdr.size,
-            ".rodata" => sizes.rodata = section.shdr.size,
-            ".text" => sizes.text = section.shdr.size,
-            _ => {}
+        let shdr = &section.shdr;
+        match classify_section(shdr.shtype == elf::types::SHT_NOBITS, shdr.flags.0) {
+            Some(Section::Bss) => sizes.bss += shdr.size,
+            Some(Section::Data) => sizes.data += shdr.size,
+            Some(Section::Rodata) => sizes.rodata += shdr.size,
+            Some(Section::Text) => sizes.text += shdr.size,
+            None => {}
         }
     }
     sizes
@@ -106,22 +141,26 @@ fn get_sizes(path: &std::path::Path) -> ElfSizes {
 
 struct ExampleData {
     name: String,
-    arch: &'static str,
+    arch: String,
     sizes: ElfSizes,
 }
 
-fn main() {
+// Gathers the sizes of every example binary found on disk, in the stable
+// (name, arch) ordering used everywhere else in this tool.
+fn collect_example_data() -> Vec<ExampleData> {
     let mut examples = find_examples();
     examples.sort_unstable();
-    let example_data: Vec<_> = examples
+    examples
         .drain(..)
         .map(|example| ExampleData {
             name: example.name,
-            arch: example.arch,
+            arch: example.arch.to_string(),
             sizes: get_sizes(&example.path),
         })
-        .collect();
+        .collect()
+}
 
+fn print_table(example_data: &[ExampleData]) {
     let name_width = 20;
     let arch_width = example_data
         .iter()
@@ -130,24 +169,21 @@ fn main() {
         .expect("No examples found");
     let section_width = 7;
 
-    // TODO: We do not currently print out .rodata's size. Currently, the linker
-    // script embeds .rodata in .text, so we don't see it as a separate section
-    // here. We should modify the linker script to put .rodata in its own
-    // section. Until that is done, .rodata's size will be counted as part of
-    // .text, so we'll just print .text's size for now.
     println!(
-        "{0:1$} {2:3$} {4:>7$} {5:>7$} {6:>7$}",
-        "Example", name_width, "Architecture", arch_width, ".bss", ".data", ".text", section_width
+        "{0:1$} {2:3$} {4:>8$} {5:>8$} {6:>8$} {7:>8$}",
+        "Example", name_width, "Architecture", arch_width, ".bss", ".data", ".rodata", ".text",
+        section_width
     );
-    for data in &example_data {
+    for data in example_data {
         println!(
-            "{0:1$} {2:3$} {4:7$} {5:7$} {6:7$}",
+            "{0:1$} {2:3$} {4:8$} {5:8$} {6:8$} {7:8$}",
             data.name,
             name_width,
             data.arch,
             arch_width,
             data.sizes.bss,
             data.sizes.data,
+            data.sizes.rodata,
             data.sizes.text,
             section_width
         );
@@ -164,15 +200,403 @@ fn main() {
             totals = &totals + &data.sizes;
         }
         println!(
-            "{0:1$} {2:3$} {4:7$} {5:7$} {6:7$}",
+            "{0:1$} {2:3$} {4:8$} {5:8$} {6:8$} {7:8$}",
             "Total",
             name_width,
             arch,
             arch_width,
             totals.bss,
             totals.data,
+            totals.rodata,
             totals.text,
             section_width
         );
     }
 }
+
+// Emits the sizes as a JSON array of per-example, per-architecture objects.
+// This is the stable artifact consumed by `--baseline`, and is what a CI job
+// would archive to diff future builds against.
+fn json_string(example_data: &[ExampleData]) -> String {
+    use std::fmt::Write;
+    let mut out = String::from("[\n");
+    for (i, data) in example_data.iter().enumerate() {
+        let comma = if i + 1 < example_data.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {{\"name\": {:?}, \"arch\": {:?}, \"bss\": {}, \"data\": {}, \"rodata\": {}, \"text\": {}}}{}",
+            data.name,
+            data.arch,
+            data.sizes.bss,
+            data.sizes.data,
+            data.sizes.rodata,
+            data.sizes.text,
+            comma
+        )
+        .unwrap();
+    }
+    out.push_str("]\n");
+    out
+}
+
+fn print_json(example_data: &[ExampleData]) {
+    print!("{}", json_string(example_data));
+}
+
+// Per-section thresholds (in bytes) that a regression must exceed before it
+// fails the diff. Defaults to zero, so any growth is a regression unless the
+// caller grants some slack on a section.
+#[derive(Default)]
+struct Thresholds {
+    bss: i64,
+    data: i64,
+    rodata: i64,
+    text: i64,
+}
+
+// Loads a baseline JSON artifact previously emitted by `--json`, compares it
+// against the current sizes (keyed on the (name, arch) ordering), prints the
+// signed per-section deltas, and exits non-zero if any regression exceeds its
+// threshold.
+fn diff_baseline(current: &[ExampleData], baseline_path: &str, thresholds: &Thresholds) {
+    let text = std::fs::read_to_string(baseline_path).expect("Unable to read baseline file");
+    let baseline = parse_baseline(&text);
+
+    // Collect the union of keys so that added and removed examples are visible
+    // in the diff too.
+    let mut keys: Vec<(String, String)> = Vec::new();
+    for data in current {
+        keys.push((data.name.clone(), data.arch.clone()));
+    }
+    for data in &baseline {
+        let key = (data.name.clone(), data.arch.clone());
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.sort_unstable();
+
+    let name_width = 20;
+    let arch_width = ARCHITECTURES.iter().map(|a| a.len()).max().unwrap();
+    let section_width = 8;
+    println!(
+        "{0:1$} {2:3$} {4:>8$} {5:>8$} {6:>8$} {7:>8$}",
+        "Example", name_width, "Architecture", arch_width, ".bss", ".data", ".rodata", ".text",
+        section_width
+    );
+
+    let lookup = |set: &[ExampleData], key: &(String, String)| -> (i64, i64, i64, i64) {
+        set.iter()
+            .find(|d| d.name == key.0 && d.arch == key.1)
+            .map(|d| {
+                (
+                    d.sizes.bss as i64,
+                    d.sizes.data as i64,
+                    d.sizes.rodata as i64,
+                    d.sizes.text as i64,
+                )
+            })
+            .unwrap_or((0, 0, 0, 0))
+    };
+
+    let mut regressed = false;
+    for key in &keys {
+        let (cbss, cdata, crodata, ctext) = lookup(current, key);
+        let (bbss, bdata, brodata, btext) = lookup(&baseline, key);
+        let dbss = cbss - bbss;
+        let ddata = cdata - bdata;
+        let drodata = crodata - brodata;
+        let dtext = ctext - btext;
+        println!(
+            "{0:1$} {2:3$} {4:>+8$} {5:>+8$} {6:>+8$} {7:>+8$}",
+            key.0, name_width, key.1, arch_width, dbss, ddata, drodata, dtext, section_width
+        );
+        if dbss > thresholds.bss
+            || ddata > thresholds.data
+            || drodata > thresholds.rodata
+            || dtext > thresholds.text
+        {
+            regressed = true;
+        }
+    }
+
+    if regressed {
+        eprintln!("error: code size regression exceeds the configured threshold");
+        std::process::exit(1);
+    }
+}
+
+// A single entry read back from a baseline artifact.
+struct BaselineEntry {
+    name: String,
+    arch: String,
+    sizes: ElfSizes,
+}
+
+// Minimal JSON value, covering exactly the subset `print_json` emits: an array
+// of flat objects whose values are strings or unsigned integers.
+enum Json {
+    Str(String),
+    Num(u64),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// Parses a baseline artifact into `ExampleData` so it can share the diff code
+// path. We hand-roll the parser to avoid pulling in a JSON dependency for a
+// format we fully control.
+fn parse_baseline(text: &str) -> Vec<ExampleData> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pos = 0;
+    let value = parse_value(&chars, &mut pos);
+    let array = match value {
+        Json::Array(entries) => entries,
+        _ => panic!("Baseline file must be a JSON array"),
+    };
+    array
+        .into_iter()
+        .map(|entry| {
+            let object = match entry {
+                Json::Object(fields) => fields,
+                _ => panic!("Baseline entries must be JSON objects"),
+            };
+            let mut builder = BaselineEntry {
+                name: String::new(),
+                arch: String::new(),
+                sizes: ElfSizes {
+                    bss: 0,
+                    data: 0,
+                    rodata: 0,
+                    text: 0,
+                },
+            };
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("name", Json::Str(s)) => builder.name = s,
+                    ("arch", Json::Str(s)) => builder.arch = s,
+                    ("bss", Json::Num(n)) => builder.sizes.bss = n,
+                    ("data", Json::Num(n)) => builder.sizes.data = n,
+                    ("rodata", Json::Num(n)) => builder.sizes.rodata = n,
+                    ("text", Json::Num(n)) => builder.sizes.text = n,
+                    _ => {}
+                }
+            }
+            ExampleData {
+                name: builder.name,
+                arch: builder.arch,
+                sizes: builder.sizes,
+            }
+        })
+        .collect()
+}
+
+fn skip_whitespace(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos += 1;
+    }
+}
+
+fn parse_value(chars: &[char], pos: &mut usize) -> Json {
+    skip_whitespace(chars, pos);
+    match chars.get(*pos) {
+        Some('[') => parse_array(chars, pos),
+        Some('{') => parse_object(chars, pos),
+        Some('"') => Json::Str(parse_string(chars, pos)),
+        Some(c) if c.is_ascii_digit() => parse_number(chars, pos),
+        other => panic!("Unexpected token in baseline JSON: {:?}", other),
+    }
+}
+
+fn parse_array(chars: &[char], pos: &mut usize) -> Json {
+    *pos += 1; // consume '['
+    let mut items = Vec::new();
+    loop {
+        skip_whitespace(chars, pos);
+        if chars.get(*pos) == Some(&']') {
+            *pos += 1;
+            break;
+        }
+        items.push(parse_value(chars, pos));
+        skip_whitespace(chars, pos);
+        if chars.get(*pos) == Some(&',') {
+            *pos += 1;
+        }
+    }
+    Json::Array(items)
+}
+
+fn parse_object(chars: &[char], pos: &mut usize) -> Json {
+    *pos += 1; // consume '{'
+    let mut fields = Vec::new();
+    loop {
+        skip_whitespace(chars, pos);
+        if chars.get(*pos) == Some(&'}') {
+            *pos += 1;
+            break;
+        }
+        let key = parse_string(chars, pos);
+        skip_whitespace(chars, pos);
+        assert_eq!(chars.get(*pos), Some(&':'), "Expected ':' in baseline JSON");
+        *pos += 1;
+        let value = parse_value(chars, pos);
+        fields.push((key, value));
+        skip_whitespace(chars, pos);
+        if chars.get(*pos) == Some(&',') {
+            *pos += 1;
+        }
+    }
+    Json::Object(fields)
+}
+
+fn parse_string(chars: &[char], pos: &mut usize) -> String {
+    assert_eq!(chars.get(*pos), Some(&'"'), "Expected '\"' in baseline JSON");
+    *pos += 1; // consume opening quote
+    let mut out = String::new();
+    while let Some(&c) = chars.get(*pos) {
+        *pos += 1;
+        match c {
+            '"' => return out,
+            '\\' => {
+                if let Some(&escaped) = chars.get(*pos) {
+                    *pos += 1;
+                    out.push(escaped);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    panic!("Unterminated string in baseline JSON");
+}
+
+fn parse_number(chars: &[char], pos: &mut usize) -> Json {
+    let mut digits = String::new();
+    while let Some(&c) = chars.get(*pos) {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            *pos += 1;
+        } else {
+            break;
+        }
+    }
+    Json::Num(digits.parse().expect("Invalid number in baseline JSON"))
+}
+
+fn parse_threshold(args: &mut std::iter::Peekable<std::env::Args>, section: &str) -> i64 {
+    args.next()
+        .unwrap_or_else(|| panic!("Missing value for {}", section))
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid value for {}", section))
+}
+
+fn main() {
+    let mut args = std::env::args().peekable();
+    let _program = args.next();
+
+    let mut json = false;
+    let mut baseline: Option<String> = None;
+    let mut thresholds = Thresholds::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--baseline" => {
+                baseline = Some(args.next().expect("Missing path for --baseline"));
+            }
+            "--threshold-bss" => thresholds.bss = parse_threshold(&mut args, "--threshold-bss"),
+            "--threshold-data" => thresholds.data = parse_threshold(&mut args, "--threshold-data"),
+            "--threshold-rodata" => {
+                thresholds.rodata = parse_threshold(&mut args, "--threshold-rodata")
+            }
+            "--threshold-text" => thresholds.text = parse_threshold(&mut args, "--threshold-text"),
+            other => panic!("Unrecognized argument: {}", other),
+        }
+    }
+
+    let example_data = collect_example_data();
+
+    if let Some(baseline_path) = baseline {
+        diff_baseline(&example_data, &baseline_path, &thresholds);
+    } else if json {
+        print_json(&example_data);
+    } else {
+        print_table(&example_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trips_through_parse_baseline() {
+        let data = vec![
+            ExampleData {
+                name: "blink".to_string(),
+                arch: "thumbv7em-none-eabi".to_string(),
+                sizes: ElfSizes {
+                    bss: 16,
+                    data: 4,
+                    rodata: 128,
+                    text: 2048,
+                },
+            },
+            ExampleData {
+                name: "console".to_string(),
+                arch: "riscv32imc-unknown-none-elf".to_string(),
+                sizes: ElfSizes {
+                    bss: 0,
+                    data: 0,
+                    rodata: 32,
+                    text: 4096,
+                },
+            },
+        ];
+
+        let parsed = parse_baseline(&json_string(&data));
+
+        assert_eq!(parsed.len(), data.len());
+        for (a, b) in parsed.iter().zip(data.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.arch, b.arch);
+            assert_eq!(a.sizes.bss, b.sizes.bss);
+            assert_eq!(a.sizes.data, b.sizes.data);
+            assert_eq!(a.sizes.rodata, b.sizes.rodata);
+            assert_eq!(a.sizes.text, b.sizes.text);
+        }
+    }
+
+    #[test]
+    fn classify_section_attributes_by_flags() {
+        let alloc = elf::types::SHF_ALLOC.0;
+        let exec = elf::types::SHF_EXECINSTR.0;
+        let write = elf::types::SHF_WRITE.0;
+
+        // Non-allocated sections (e.g. .debug_*, .comment) contribute nothing.
+        assert!(classify_section(false, 0).is_none());
+        assert!(classify_section(false, exec).is_none());
+
+        // A synthetic allocated section table covering every bucket.
+        assert!(matches!(
+            classify_section(true, alloc | write),
+            Some(Section::Bss)
+        ));
+        assert!(matches!(
+            classify_section(false, alloc | exec),
+            Some(Section::Text)
+        ));
+        // A section that is both executable and writable counts as .text: the
+        // executable flag is checked before the writable one.
+        assert!(matches!(
+            classify_section(false, alloc | exec | write),
+            Some(Section::Text)
+        ));
+        assert!(matches!(
+            classify_section(false, alloc | write),
+            Some(Section::Data)
+        ));
+        assert!(matches!(
+            classify_section(false, alloc),
+            Some(Section::Rodata)
+        ));
+    }
+}